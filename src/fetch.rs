@@ -0,0 +1,534 @@
+// GitHub fetcher: pulls select/index.json + LRC from each repo under LRCHub
+// and compiles them into a SQLite DB. Records each repo's last-seen manifest
+// ETag in `lrc_cache.json` so unchanged repos are skipped rather than
+// re-downloaded (incremental / resumable build).
+
+use crate::report::write_report;
+use brotli::enc::BrotliEncoderParams;
+use clap::Args;
+use futures::stream::{self, StreamExt};
+use rand::Rng;
+use reqwest::{header, Client, Response};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+pub(crate) const CACHE_NAME: &str = "lrc_cache.json";
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+#[derive(Args)]
+pub struct FetchArgs {
+    /// GitHub org/user that owns the per-video LRC repositories
+    #[arg(long, default_value = "LRCHub")]
+    org: String,
+    /// Max number of concurrent in-flight repo fetches
+    #[arg(long, default_value_t = 200)]
+    concurrency: usize,
+    /// Brotli quality (0-11) used to compress the compiled DB
+    #[arg(long, default_value_t = 11)]
+    brotli_quality: i32,
+    /// Path of the compiled SQLite DB (the Brotli artifact is written to "<db>.br")
+    #[arg(long, default_value = "lyrics.db")]
+    db: String,
+    /// Per-request timeout (seconds) for the reqwest::Client used for both the
+    /// GraphQL repo listing and the manifest/LRC raw-content fetches
+    #[arg(long, default_value_t = 20)]
+    timeout_secs: u64,
+    /// Max retry attempts for a single request on network errors, timeouts, and
+    /// 429/5xx responses, backing off `base * 2^attempt` (plus jitter) between tries
+    #[arg(long, default_value_t = 4)]
+    max_retries: u32,
+    /// Write a structured build report (JSON by default, see REPORT_FORMAT) to this path
+    #[arg(long)]
+    report: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    candidates: Option<Vec<Candidate>>,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    has_synced: Option<bool>,
+    path: Option<String>,
+}
+
+struct DbRecord {
+    video_id: String,
+    is_synced: bool,
+    lrc: String,
+}
+
+/// Each repo's last-seen manifest ETag and selected candidate, used to
+/// skip unchanged repos instead of re-downloading them.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct CacheEntry {
+    etag: Option<String>,
+    is_synced: bool,
+    path: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct CacheManifest {
+    pub(crate) repos: HashMap<String, CacheEntry>,
+}
+
+impl CacheManifest {
+    pub(crate) fn load(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub(crate) fn save(&self, path: &str) -> std::io::Result<()> {
+        let text = serde_json::to_string(self).unwrap_or_default();
+        std::fs::write(path, text)
+    }
+}
+
+/// Per-run counters. Tallies successes/retries/permanent failures per
+/// request so they're reflected in the stderr stats instead of vanishing.
+#[derive(Default)]
+struct FetchCounters {
+    fetched: AtomicU64,
+    retried: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Structured build report for `--report`/`REPORT_FORMAT`. CI parses this
+/// to detect regressions (e.g. a sharp drop in records inserted).
+#[derive(Serialize)]
+struct FetchReport {
+    repos_discovered: usize,
+    records_inserted: u64,
+    retried: u64,
+    failed: u64,
+    original_size_bytes: u64,
+    compressed_size_bytes: u64,
+    elapsed_secs: f64,
+}
+
+/// Sends a GET, retrying network errors, timeouts, and 429/5xx responses up
+/// to `max_retries` times, backing off `base * 2^attempt` plus jitter.
+/// Honors a `Retry-After` header given in seconds; an HTTP-date value is not
+/// parsed and the backoff delay is used instead.
+async fn get_with_retry<F>(make_req: F, counters: &FetchCounters, max_retries: u32) -> Option<Response>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut attempt = 0;
+    loop {
+        let res = make_req().send().await;
+
+        match res {
+            Ok(res) if res.status().is_success() || res.status() == reqwest::StatusCode::NOT_MODIFIED => {
+                counters.fetched.fetch_add(1, Ordering::Relaxed);
+                return Some(res);
+            }
+            Ok(res) if is_retryable_status(res.status()) && attempt < max_retries => {
+                let delay = retry_after_delay(&res).unwrap_or_else(|| backoff_delay(attempt));
+                log::warn!(
+                    "Transient HTTP {} on attempt {}/{}, retrying in {:?}",
+                    res.status(), attempt + 1, max_retries, delay
+                );
+                counters.retried.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Ok(_) => {
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            Err(e) if attempt < max_retries => {
+                let delay = backoff_delay(attempt);
+                log::warn!(
+                    "Request error on attempt {}/{}: {}, retrying in {:?}",
+                    attempt + 1, max_retries, e, delay
+                );
+                counters.retried.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                log::error!("Permanently failed after {} attempts: {}", attempt + 1, e);
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        }
+    }
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn retry_after_delay(res: &Response) -> Option<Duration> {
+    let secs: u64 = res
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(secs))
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    let base = RETRY_BASE_DELAY_MS * 2u64.pow(attempt);
+    let jitter = rand::thread_rng().gen_range(0..=100);
+    Duration::from_millis(base + jitter)
+}
+
+pub async fn run(args: FetchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let run_start = std::time::Instant::now();
+    let report_path = args.report.clone();
+
+    let compiled_db = format!("{}.br", args.db);
+
+    let github_token = env::var("GITHUB_TOKEN").unwrap_or_default();
+
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        header::ACCEPT,
+        header::HeaderValue::from_static("application/vnd.github.v3+json"),
+    );
+    headers.insert(
+        header::USER_AGENT,
+        header::HeaderValue::from_static("lrc-db-builder/1.0"),
+    );
+    if !github_token.is_empty() {
+        if let Ok(val) = header::HeaderValue::from_str(&format!("token {}", github_token)) {
+            headers.insert(header::AUTHORIZATION, val);
+        }
+    }
+
+    let client = Client::builder()
+        .user_agent("lrc-db-builder/1.0")
+        .default_headers(headers)
+        .timeout(Duration::from_secs(args.timeout_secs))
+        .build()?;
+
+    log::info!("Fetching repository list using GraphQL from {}...", args.org);
+    let mut repos = Vec::new();
+    let mut has_next_page = true;
+    let mut end_cursor: Option<String> = None;
+
+    while has_next_page {
+        let after_clause = match &end_cursor {
+            Some(cursor) => format!(r#", after: "{}""#, cursor),
+            None => "".to_string(),
+        };
+
+        let query = format!(
+            r#"{{
+                "query": "query {{ user(login: \"{}\") {{ repositories(first: 100{}) {{ pageInfo {{ hasNextPage endCursor }} nodes {{ name }} }} }} }}"
+            }}"#,
+            args.org, after_clause
+        );
+
+        let resp = client
+            .post("https://api.github.com/graphql")
+            .body(query)
+            .send()
+            .await;
+
+        match resp {
+            Ok(res) if res.status().is_success() => {
+                if let Ok(data) = res.json::<serde_json::Value>().await {
+                    let repos_node = &data["data"]["user"]["repositories"];
+
+                    if let Some(nodes) = repos_node["nodes"].as_array() {
+                        for node in nodes {
+                            if let Some(name) = node["name"].as_str() {
+                                if !name.starts_with('.') {
+                                    repos.push(name.to_string());
+                                }
+                            }
+                        }
+                    }
+
+                    has_next_page = repos_node["pageInfo"]["hasNextPage"].as_bool().unwrap_or(false);
+                    if has_next_page {
+                        end_cursor = repos_node["pageInfo"]["endCursor"].as_str().map(|s| s.to_string());
+                    }
+                } else {
+                    break;
+                }
+            }
+            Ok(res) => {
+                let status = res.status();
+                let txt = res.text().await.unwrap_or_default();
+                log::error!("GraphQL failed: HTTP {} - {}", status, txt);
+                if status == 403 || status == 429 {
+                    log::warn!("Rate limited. Waiting 10 seconds...");
+                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                    continue; // retry
+                }
+                break;
+            }
+            Err(e) => {
+                log::error!("Error fetching from GraphQL: {}", e);
+                break;
+            }
+        }
+    }
+
+    log::info!("Discovered {} video repositories.", repos.len());
+    let repos_discovered = repos.len();
+
+    let cache = CacheManifest::load(CACHE_NAME);
+    log::info!("Loaded cache with {} known repositories.", cache.repos.len());
+    let new_cache = std::sync::Arc::new(std::sync::Mutex::new(CacheManifest::default()));
+
+    // Incremental builds keep the existing DB around and merge changed rows via
+    // INSERT OR REPLACE, instead of recreating it from scratch on every run.
+    let db_exists = Path::new(&args.db).exists();
+
+    // Producer / Consumer channel for DB insertions
+    let (tx, mut rx) = mpsc::channel::<DbRecord>(5000);
+
+    log::info!(
+        "{} {} for incremental merge.",
+        if db_exists { "Reusing" } else { "Creating" },
+        args.db
+    );
+
+    let db_path = args.db.clone();
+    let db_thread = tokio::task::spawn_blocking(move || {
+        let mut conn = Connection::open(&db_path).expect("Failed to open DB");
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lyrics (
+                video_id TEXT PRIMARY KEY,
+                is_synced BOOLEAN NOT NULL,
+                lrc TEXT NOT NULL
+            )",
+            [],
+        )
+        .expect("Failed to create table");
+
+        let mut temp_buffer = Vec::new();
+        let mut count = 0;
+
+        while let Some(record) = rx.blocking_recv() {
+            temp_buffer.push(record);
+            count += 1;
+
+            if temp_buffer.len() >= 1000 {
+                let tx_db = conn.transaction().unwrap();
+                {
+                    let mut stmt = tx_db
+                        .prepare("INSERT OR REPLACE INTO lyrics (video_id, is_synced, lrc) VALUES (?1, ?2, ?3)")
+                        .unwrap();
+                    for r in &temp_buffer {
+                        stmt.execute(rusqlite::params![r.video_id, r.is_synced, r.lrc])
+                            .unwrap();
+                    }
+                }
+                tx_db.commit().unwrap();
+                temp_buffer.clear();
+                log::info!("Database sync: {} inserted.", count);
+            }
+        }
+
+        if !temp_buffer.is_empty() {
+            let tx_db = conn.transaction().unwrap();
+            {
+                let mut stmt = tx_db
+                    .prepare("INSERT OR REPLACE INTO lyrics (video_id, is_synced, lrc) VALUES (?1, ?2, ?3)")
+                    .unwrap();
+                for r in &temp_buffer {
+                    stmt.execute(rusqlite::params![r.video_id, r.is_synced, r.lrc])
+                        .unwrap();
+                }
+            }
+            tx_db.commit().unwrap();
+        }
+
+        log::info!("Optimizing database with VACUUM...");
+        conn.execute("VACUUM", []).unwrap();
+        log::info!("Database writer finished. Total compiled records: {}", count);
+        count
+    });
+
+    log::info!(
+        "Starting ultra-parallel fetch ({} concurrency) for {} videos...",
+        args.concurrency,
+        repos.len()
+    );
+
+    let client_ref = &client;
+    let tx_ref = &tx;
+    let cache_ref = &cache;
+    let new_cache_ref = &new_cache;
+    let org_ref = args.org.as_str();
+    let counters = FetchCounters::default();
+    let counters_ref = &counters;
+    let max_retries = args.max_retries;
+
+    stream::iter(repos)
+        .map(|video_id| async move {
+            let manifest_url = format!(
+                "https://raw.githubusercontent.com/{}/{}/main/select/index.json",
+                org_ref, video_id
+            );
+
+            let prev_entry = cache_ref.repos.get(&video_id).cloned();
+            let if_none_match = prev_entry.as_ref().and_then(|e| e.etag.clone());
+
+            let make_manifest_req = || {
+                let mut req = client_ref.get(&manifest_url);
+                if let Some(etag) = &if_none_match {
+                    if let Ok(val) = header::HeaderValue::from_str(etag) {
+                        req = req.header(header::IF_NONE_MATCH, val);
+                    }
+                }
+                req
+            };
+
+            let res = match get_with_retry(make_manifest_req, counters_ref, max_retries).await {
+                Some(res) => res,
+                None => return,
+            };
+
+            // 304: the manifest hasn't changed since last run, so skip the
+            // re-download and carry the previous selection into the new cache.
+            if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+                if let Some(entry) = prev_entry {
+                    new_cache_ref
+                        .lock()
+                        .unwrap()
+                        .repos
+                        .insert(video_id, entry);
+                }
+                return;
+            }
+
+            let etag = res
+                .headers()
+                .get(header::ETAG)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+
+            let manifest_text = match res.text().await {
+                Ok(text) => text,
+                Err(_) => return,
+            };
+            let manifest: Manifest = match serde_json::from_str(&manifest_text) {
+                Ok(m) => m,
+                Err(_) => return,
+            };
+
+            let candidates = match manifest.candidates {
+                Some(c) if !c.is_empty() => c,
+                _ => return,
+            };
+
+            let mut best = &candidates[0];
+            for c in &candidates {
+                if c.has_synced.unwrap_or(false) {
+                    best = c;
+                    break;
+                }
+            }
+
+            let path = match &best.path {
+                Some(p) => p.clone(),
+                None => return,
+            };
+            let is_synced = best.has_synced.unwrap_or(false);
+
+            let encoded_path = path
+                .split('/')
+                .map(|p| urlencoding::encode(p).into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let lrc_url = format!(
+                "https://raw.githubusercontent.com/{}/{}/main/{}",
+                org_ref, video_id, encoded_path
+            );
+
+            if let Some(lrc_res) =
+                get_with_retry(|| client_ref.get(&lrc_url), counters_ref, max_retries).await
+            {
+                if let Ok(lrc_content) = lrc_res.text().await {
+                    new_cache_ref.lock().unwrap().repos.insert(
+                        video_id.clone(),
+                        CacheEntry { etag, is_synced, path },
+                    );
+                    let _ = tx_ref
+                        .send(DbRecord {
+                            video_id,
+                            is_synced,
+                            lrc: lrc_content,
+                        })
+                        .await;
+                }
+            }
+        })
+        .buffer_unordered(args.concurrency)
+        .collect::<Vec<()>>()
+        .await;
+
+    drop(tx);
+    let records_inserted = db_thread.await.unwrap_or(0) as u64;
+
+    log::info!(
+        "Fetch pipeline: {} fetched, {} retried, {} permanently failed.",
+        counters.fetched.load(Ordering::Relaxed),
+        counters.retried.load(Ordering::Relaxed),
+        counters.failed.load(Ordering::Relaxed),
+    );
+
+    if let Err(e) = new_cache.lock().unwrap().save(CACHE_NAME) {
+        log::warn!("Failed to persist cache manifest {}: {}", CACHE_NAME, e);
+    }
+
+    log::info!("Compressing database with Brotli (Quality {})...", args.brotli_quality);
+    let mut file = File::open(&args.db)?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+
+    let mut compressed_data = Vec::new();
+    let params = BrotliEncoderParams { quality: args.brotli_quality, ..Default::default() };
+
+    let mut reader = Cursor::new(data.clone());
+    brotli::BrotliCompress(&mut reader, &mut compressed_data, &params)?;
+
+    let mut out_file = File::create(&compiled_db)?;
+    out_file.write_all(&compressed_data)?;
+
+    let orig_len = data.len() as f64 / 1_048_576.0;
+    let comp_len = compressed_data.len() as f64 / 1_048_576.0;
+
+    log::info!(
+        "Compression complete. Original: {:.2} MB -> Compressed: {:.2} MB",
+        orig_len, comp_len
+    );
+
+    if let Some(path) = report_path {
+        let report = FetchReport {
+            repos_discovered,
+            records_inserted,
+            retried: counters.retried.load(Ordering::Relaxed),
+            failed: counters.failed.load(Ordering::Relaxed),
+            original_size_bytes: data.len() as u64,
+            compressed_size_bytes: compressed_data.len() as u64,
+            elapsed_secs: run_start.elapsed().as_secs_f64(),
+        };
+        if let Err(e) = write_report(&path, &report) {
+            log::warn!("Failed to write report to {}: {}", path, e);
+        }
+    }
+
+    Ok(())
+}
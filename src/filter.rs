@@ -0,0 +1,530 @@
+// lrclib filter: 34GB raw DB -> dedup + lang filter + quality filter -> lightweight DB
+//
+// Single-pass streaming:
+//   1. Quality check (line count >= --min-lines, byte length >= --min-chars)
+//   2. Lang detection (only languages allowed by --lang, single scan + early return)
+//   3. Lyrics fingerprint dedup (MD5, global)
+//   4. Near-duplicate dedup (--near-dup: MinHash + LSH banding, optional)
+//   5. Metadata dedup (normalized artist+track+duration)
+
+use crate::report::write_report;
+use clap::Args;
+use md5::{Digest, Md5};
+use rand::Rng;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+
+/// MinHash signature length. Must match `LSH_BANDS * LSH_ROWS`.
+const MINHASH_K: usize = 128;
+/// Number of LSH bands. Chosen so the band/row ratio (1/LSH_BANDS)^(1/LSH_ROWS) lands
+/// close to the target threshold 0.8 (among power-of-two divisors of k=128, b=8,r=16
+/// is the closest at ~0.88).
+const LSH_BANDS: usize = 8;
+const LSH_ROWS: usize = MINHASH_K / LSH_BANDS;
+/// Estimated Jaccard similarity at or above this is treated as a near-duplicate.
+const LSH_JACCARD_THRESHOLD: f64 = 0.8;
+
+#[derive(Args)]
+pub struct FilterArgs {
+    /// Path to the raw input DB (lrclib-shaped "lyrics" table)
+    input_db: String,
+    /// Path to write the filtered, indexed output DB to
+    output_db: String,
+    /// Write a structured filter report (JSON by default, see REPORT_FORMAT) to this path
+    #[arg(long)]
+    report: Option<String>,
+    /// Minimum number of lines a lyric must have to be kept
+    #[arg(long, default_value_t = 10)]
+    min_lines: usize,
+    /// Minimum byte length of the timestamp-stripped, trimmed lyric text for it to be kept
+    #[arg(long, default_value_t = 100)]
+    min_chars: usize,
+    /// Minimum track duration in seconds to be considered (NULL durations are always kept)
+    #[arg(long, default_value_t = 60.0)]
+    min_duration: f64,
+    /// Comma-separated list of languages to keep (matches classify_lang's output: ja, ko, en)
+    #[arg(long, value_delimiter = ',', default_value = "ja,ko,en")]
+    lang: Vec<String>,
+    /// Minimum byte length of timestamp-stripped lyrics text before classify_lang
+    /// attempts detection at all (too-short text is always classified as unknown)
+    #[arg(long, default_value_t = 30)]
+    lang_min_bytes: usize,
+    /// Number of ja/ko script characters classify_lang requires before committing
+    /// to that language
+    #[arg(long, default_value_t = 10)]
+    lang_script_count: u32,
+    /// Number of Latin characters classify_lang requires before committing to "en"
+    #[arg(long, default_value_t = 30)]
+    lang_latin_count: u32,
+    /// Max count of excluded-script (Arabic/Cyrillic/Devanagari/Thai) characters
+    /// classify_lang tolerates before bailing out to unknown
+    #[arg(long, default_value_t = 50)]
+    lang_exclude_count: u32,
+    /// Also catch near-duplicate lyrics (MinHash + LSH banding over word 3-gram
+    /// shingles, estimated Jaccard >= ~0.8) in addition to exact fingerprint dedup
+    #[arg(long)]
+    near_dup: bool,
+}
+
+/// Thresholds for `classify_lang`. Overridable via `--lang-*` flags
+/// (originally constants hardcoded inside the function).
+pub(crate) struct LangThresholds {
+    pub(crate) min_bytes: usize,
+    pub(crate) script_count: u32,
+    pub(crate) latin_count: u32,
+    pub(crate) exclude_count: u32,
+}
+
+impl Default for LangThresholds {
+    fn default() -> Self {
+        Self { min_bytes: 30, script_count: 10, latin_count: 30, exclude_count: 50 }
+    }
+}
+
+// ============================================================
+// Language detection
+// ============================================================
+
+pub(crate) fn classify_lang(text_no_ts: &str, thresholds: &LangThresholds) -> Option<&'static str> {
+    // NOTE: len() is byte length. CJK text is 3 bytes/char, so 30 bytes is
+    // ~10 chars. Looser than the Python version's 30 chars, but this is only
+    // meant to filter out text that's too short to classify, so it's fine.
+    if text_no_ts.len() < thresholds.min_bytes {
+        return None;
+    }
+    let (mut ja, mut ko, mut latin, mut exclude) = (0u32, 0u32, 0u32, 0u32);
+    for c in text_no_ts.chars() {
+        let cp = c as u32;
+        if (0x3040..=0x309F).contains(&cp) || (0x30A0..=0x30FF).contains(&cp) {
+            ja += 1;
+            if ja >= thresholds.script_count { return Some("ja"); }
+        } else if (0xAC00..=0xD7AF).contains(&cp) {
+            ko += 1;
+            if ko >= thresholds.script_count { return Some("ko"); }
+        } else if (0x41..=0x5A).contains(&cp) || (0x61..=0x7A).contains(&cp)
+                || (0xC0..=0x24F).contains(&cp) {
+            latin += 1;
+        } else if (0x600..=0x6FF).contains(&cp) || (0x400..=0x4FF).contains(&cp)
+                || (0x900..=0x97F).contains(&cp) || (0xE00..=0xE7F).contains(&cp) {
+            exclude += 1;
+            if exclude > thresholds.exclude_count { return None; }
+        }
+    }
+    if exclude > thresholds.exclude_count && exclude > latin { return None; }
+    if latin >= thresholds.latin_count { return Some("en"); }
+    None
+}
+
+// ============================================================
+// Normalization
+// ============================================================
+
+fn normalize_name(name: &str, re_symbol: &Regex, re_bracket: &Regex, re_feat: &Regex) -> String {
+    let n = name.to_lowercase();
+    // Strip brackets first (before re_symbol strips the parens themselves)
+    let n = re_bracket.replace_all(&n, "");
+    let n = re_feat.replace_all(&n, "");
+    let n = re_symbol.replace_all(&n, "");
+    n.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// ============================================================
+// Lyrics fingerprint
+// ============================================================
+
+/// text_no_ts: timestamp-stripped text (already computed by the quality check)
+pub(crate) fn lyrics_fingerprint(text_no_ts: &str, re_paren: &Regex, re_non_word: &Regex) -> Option<[u8; 16]> {
+    let text = re_paren.replace_all(text_no_ts, "");
+    let text = re_non_word.replace_all(&text, "");
+    let text = text.to_lowercase();
+    if text.is_empty() { return None; }
+    let mut hasher = Md5::new();
+    hasher.update(text.as_bytes());
+    Some(hasher.finalize().into())
+}
+
+// ============================================================
+// Near-duplicate detection (MinHash + LSH)
+// ============================================================
+
+/// Splits text_no_ts into a shingle set. Uses word 3-grams when there are
+/// enough words, falling back to line-level tokens for too-short (sparse) lyrics.
+fn minhash_shingles(text_no_ts: &str, re_symbol: &Regex) -> HashSet<String> {
+    let lowered = text_no_ts.to_lowercase();
+    let cleaned = re_symbol.replace_all(&lowered, "");
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    if words.len() >= 3 {
+        words.windows(3).map(|w| w.join(" ")).collect()
+    } else {
+        text_no_ts
+            .lines()
+            .map(|l| l.trim().to_lowercase())
+            .filter(|l| !l.is_empty())
+            .collect()
+    }
+}
+
+fn hash_str(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+// A MurmurHash3 fmix64-style bit mixer. Combines a shingle's base hash with k seeds
+// to cheaply derive a family of pseudo-independent hash functions.
+fn mix64(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^= x >> 33;
+    x
+}
+
+/// Computes a `seeds.len()`-wide MinHash signature from a shingle set.
+/// Returns None for an empty shingle set, letting the caller fall back to
+/// the existing exact-match MD5 path.
+fn minhash_signature(shingles: &HashSet<String>, seeds: &[u64]) -> Option<Vec<u32>> {
+    if shingles.is_empty() {
+        return None;
+    }
+    let mut sig = vec![u32::MAX; seeds.len()];
+    for shingle in shingles {
+        let base = hash_str(shingle);
+        for (i, &seed) in seeds.iter().enumerate() {
+            let h = mix64(base ^ seed) as u32;
+            if h < sig[i] {
+                sig[i] = h;
+            }
+        }
+    }
+    Some(sig)
+}
+
+fn lsh_band_hash(band: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    band.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn estimated_jaccard(a: &[u32], b: &[u32]) -> f64 {
+    a.iter().zip(b.iter()).filter(|(x, y)| x == y).count() as f64 / a.len() as f64
+}
+
+/// Removes `id` from every band bucket its signature belonged to. Always
+/// call this together with removing from `kept_signatures`, so a superseded
+/// kept record never lingers in `lsh_bands`.
+fn purge_from_bands(lsh_bands: &mut [HashMap<u64, Vec<i64>>], sig: &[u32], id: i64) {
+    for (b, band_map) in lsh_bands.iter_mut().enumerate() {
+        let start = b * LSH_ROWS;
+        let key = lsh_band_hash(&sig[start..start + LSH_ROWS]);
+        if let Some(ids) = band_map.get_mut(&key) {
+            ids.retain(|&x| x != id);
+            if ids.is_empty() {
+                band_map.remove(&key);
+            }
+        }
+    }
+}
+
+// ============================================================
+// Report output
+// ============================================================
+
+/// Structured report for `--report`/`REPORT_FORMAT`. CI parses this to
+/// mechanically detect regressions like "kept dropped by >5%".
+#[derive(Serialize)]
+struct FilterReport {
+    quality: u64,
+    lang: u64,
+    fp_dedup: u64,
+    near_dup: u64,
+    meta_dedup: u64,
+    kept: u64,
+    kept_by_lang: HashMap<String, i64>,
+    elapsed_secs: f64,
+}
+
+// ============================================================
+// Main
+// ============================================================
+
+pub fn run(args: FilterArgs) -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("Input:  {}", args.input_db);
+    eprintln!("Output: {}", args.output_db);
+
+    // Precompiled regexes
+    let re_ts = Regex::new(r"\[[\d:.]+\]").unwrap();
+    let re_paren = Regex::new(r"[(（][^()（）]*[)）]").unwrap();
+    let re_non_word = Regex::new(r"[^\w]").unwrap();
+    let re_symbol = Regex::new(r"[^\w\s]").unwrap();
+    let re_bracket = Regex::new(r"\s*[(（\[【].+?[)）\]】]").unwrap();
+    let re_feat = Regex::new(r"(?i)\s*(feat|ft|with)\s+.*$").unwrap();
+
+    // classify_lang thresholds (overridable via --lang-*)
+    let lang_thresholds = LangThresholds {
+        min_bytes: args.lang_min_bytes,
+        script_count: args.lang_script_count,
+        latin_count: args.lang_latin_count,
+        exclude_count: args.lang_exclude_count,
+    };
+
+    // Input DB
+    let src = Connection::open(&args.input_db).expect("Failed to open input DB");
+    src.execute_batch("PRAGMA mmap_size=4294967296; PRAGMA cache_size=-1000000;").unwrap();
+    let total: i64 = src.query_row("SELECT COUNT(*) FROM lyrics", [], |r| r.get(0)).unwrap();
+    eprintln!("Total records: {}", total);
+
+    // Output DB
+    let dst = Connection::open(&args.output_db).expect("Failed to open output DB");
+    dst.execute_batch(
+        "PRAGMA synchronous=OFF; PRAGMA journal_mode=OFF; PRAGMA cache_size=-500000;
+         CREATE TABLE lyrics (
+             id INTEGER PRIMARY KEY, track_name TEXT NOT NULL,
+             artist_name TEXT NOT NULL, album_name TEXT,
+             duration REAL, synced_lyrics TEXT NOT NULL, lang TEXT NOT NULL
+         );"
+    ).unwrap();
+
+    // Dedup data structures
+    let mut fp_seen: HashSet<[u8; 16]> = HashSet::with_capacity(5_000_000);
+    // meta_key -> (id, line_count)
+    let mut meta_seen: HashMap<String, (i64, usize)> = HashMap::with_capacity(5_000_000);
+    // id -> meta_key (reverse lookup). When near-dup dedup deletes a row unrelated
+    // to meta_seen, this lets us drop the dangling meta_seen reference if that row
+    // was also some meta_key's representative.
+    let mut id_to_meta_key: HashMap<i64, String> = HashMap::with_capacity(5_000_000);
+
+    // --near-dup data structures. Signatures are only kept for kept records (k*4
+    // bytes per id) to bound memory. Seeds are redrawn each run; they just need
+    // to be consistent within a single run.
+    let near_dup_seeds: Vec<u64> = if args.near_dup {
+        let mut rng = rand::thread_rng();
+        (0..MINHASH_K).map(|_| rng.gen()).collect()
+    } else {
+        Vec::new()
+    };
+    let mut lsh_bands: Vec<HashMap<u64, Vec<i64>>> = vec![HashMap::new(); LSH_BANDS];
+    let mut kept_signatures: HashMap<i64, (Vec<u32>, usize)> = HashMap::with_capacity(5_000_000);
+
+    let mut stats = [0u64; 6]; // quality, lang, fp_dedup, near_dup, meta_dedup, kept
+    let t0 = Instant::now();
+
+    // SQL pre-filter (duration >= --min-duration)
+    let mut stmt = src.prepare(
+        "SELECT id, track_name, artist_name, album_name, duration, synced_lyrics \
+         FROM lyrics WHERE duration IS NULL OR duration >= ?1"
+    ).unwrap();
+
+    let mut rows = stmt.query(params![args.min_duration]).unwrap();
+    let mut i: u64 = 0;
+
+    dst.execute_batch("BEGIN").unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        let rid: i64 = row.get(0).unwrap();
+        let track: String = row.get(1).unwrap();
+        let artist: String = row.get(2).unwrap();
+        let album: Option<String> = row.get(3).unwrap();
+        let dur: Option<f64> = row.get(4).unwrap();
+        let lyrics: String = row.get(5).unwrap();
+
+        i += 1;
+        if i.is_multiple_of(500_000) {
+            let elapsed = t0.elapsed().as_secs_f64();
+            eprintln!("  {:>10} / {}  {:.0}/s  kept={}", i, total, i as f64 / elapsed, stats[5]);
+        }
+
+        // 1. Quality check
+        let line_count = lyrics.matches('\n').count() + 1;
+        if line_count < args.min_lines {
+            stats[0] += 1; continue;
+        }
+        let text_no_ts = re_ts.replace_all(&lyrics, "");
+        if text_no_ts.trim().len() < args.min_chars {
+            stats[0] += 1; continue;
+        }
+
+        // 2. Language detection
+        let lang = match classify_lang(&text_no_ts, &lang_thresholds) {
+            Some(l) if args.lang.iter().any(|allowed| allowed == l) => l,
+            _ => { stats[1] += 1; continue; }
+        };
+
+        // 3. Lyrics fingerprint dedup (reuses text_no_ts to avoid running re_ts twice)
+        if let Some(fp) = lyrics_fingerprint(&text_no_ts, &re_paren, &re_non_word) {
+            if !fp_seen.insert(fp) {
+                stats[2] += 1; continue;
+            }
+        }
+
+        // 4. Near-duplicate dedup (--near-dup, optional)
+        // This only identifies the candidate (near_dup_evict); it does not DELETE
+        // prev_id or register the signature (into kept_signatures/lsh_bands) yet.
+        // The row isn't actually "kept" until it also survives meta dedup and is
+        // INSERTed, so both pieces of bookkeeping are deferred until right after
+        // the INSERT (otherwise, if meta dedup then drops the current row, the
+        // near-dup family's sole surviving representative would vanish).
+        let mut near_dup_sig: Option<Vec<u32>> = None;
+        let mut near_dup_evict: Option<i64> = None;
+        if args.near_dup {
+            let shingles = minhash_shingles(&text_no_ts, &re_symbol);
+            if let Some(sig) = minhash_signature(&shingles, &near_dup_seeds) {
+                let mut dup_id = None;
+                let mut checked: HashSet<i64> = HashSet::new();
+                'bands: for (b, band_map) in lsh_bands.iter().enumerate() {
+                    let start = b * LSH_ROWS;
+                    let key = lsh_band_hash(&sig[start..start + LSH_ROWS]);
+                    if let Some(ids) = band_map.get(&key) {
+                        for &cand_id in ids {
+                            if !checked.insert(cand_id) {
+                                continue;
+                            }
+                            if let Some((cand_sig, _)) = kept_signatures.get(&cand_id) {
+                                if estimated_jaccard(&sig, cand_sig) >= LSH_JACCARD_THRESHOLD {
+                                    dup_id = Some(cand_id);
+                                    break 'bands;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if let Some(prev_id) = dup_id {
+                    let prev_lines = kept_signatures.get(&prev_id).unwrap().1;
+                    if line_count <= prev_lines {
+                        stats[3] += 1; continue;
+                    }
+                    near_dup_evict = Some(prev_id);
+                }
+
+                near_dup_sig = Some(sig);
+            }
+        }
+
+        // 5. Metadata dedup
+        // duration bucket: 10-second units (treats within ±5s as the same track)
+        let meta_key = format!("{}\t{}\t{}",
+            normalize_name(&artist, &re_symbol, &re_bracket, &re_feat),
+            normalize_name(&track, &re_symbol, &re_bracket, &re_feat),
+            ((dur.unwrap_or(0.0) / 10.0).round() as i64),
+        );
+
+        if let Some((prev_id, prev_lines)) = meta_seen.get(&meta_key) {
+            if line_count <= *prev_lines {
+                // Current row is dropped. Any near-dup eviction still isn't
+                // deleted, so the family's representative survives untouched.
+                stats[4] += 1; continue;
+            }
+            dst.execute("DELETE FROM lyrics WHERE id=?", params![*prev_id]).unwrap();
+            if let Some((prev_sig, _)) = kept_signatures.remove(prev_id) {
+                purge_from_bands(&mut lsh_bands, &prev_sig, *prev_id);
+            }
+            id_to_meta_key.remove(prev_id);
+            // Same row already queued for near-dup eviction: it's only deleted
+            // (and counted) once, via this branch.
+            if near_dup_evict == Some(*prev_id) {
+                near_dup_evict = None;
+            }
+            stats[4] += 1;
+            stats[5] -= 1;
+        }
+
+        // The row survived this far, so it's actually kept: now delete the row
+        // that near-dup eviction queued (unless meta-dedup already deleted it
+        // above, in which case near_dup_evict was cleared to skip this).
+        if let Some(prev_id) = near_dup_evict {
+            dst.execute("DELETE FROM lyrics WHERE id=?", params![prev_id]).unwrap();
+            if let Some((prev_sig, _)) = kept_signatures.remove(&prev_id) {
+                purge_from_bands(&mut lsh_bands, &prev_sig, prev_id);
+            }
+            // If prev_id was also meta_seen's representative under some other
+            // meta_key, drop that dangling reference too.
+            if let Some(old_meta_key) = id_to_meta_key.remove(&prev_id) {
+                if meta_seen.get(&old_meta_key).map(|(id, _)| *id) == Some(prev_id) {
+                    meta_seen.remove(&old_meta_key);
+                }
+            }
+            stats[3] += 1;
+            stats[5] -= 1;
+        }
+
+        meta_seen.insert(meta_key.clone(), (rid, line_count));
+        id_to_meta_key.insert(rid, meta_key);
+
+        dst.execute(
+            "INSERT INTO lyrics VALUES (?,?,?,?,?,?,?)",
+            params![rid, track, artist, album, dur, lyrics, lang],
+        ).unwrap();
+        stats[5] += 1;
+
+        // The row is now actually kept, so register the near-duplicate bookkeeping.
+        if let Some(sig) = near_dup_sig {
+            for (b, band_map) in lsh_bands.iter_mut().enumerate() {
+                let start = b * LSH_ROWS;
+                let key = lsh_band_hash(&sig[start..start + LSH_ROWS]);
+                band_map.entry(key).or_default().push(rid);
+            }
+            kept_signatures.insert(rid, (sig, line_count));
+        }
+
+        // Periodic commit
+        if stats[5] % 100_000 == 0 {
+            dst.execute_batch("COMMIT; BEGIN").unwrap();
+        }
+    }
+
+    dst.execute_batch("COMMIT").unwrap();
+    let elapsed = t0.elapsed().as_secs_f64();
+
+    eprintln!("\nFiltering done in {:.1}s", elapsed);
+    eprintln!("  quality:    {}", stats[0]);
+    eprintln!("  lang:       {}", stats[1]);
+    eprintln!("  fp_dedup:   {}", stats[2]);
+    eprintln!("  near_dup:   {}", stats[3]);
+    eprintln!("  meta_dedup: {}", stats[4]);
+    eprintln!("  kept:       {}", stats[5]);
+
+    // Indexes
+    eprintln!("Building indexes...");
+    let t1 = Instant::now();
+    dst.execute_batch(
+        "CREATE INDEX idx_artist_track ON lyrics(artist_name, track_name);
+         CREATE INDEX idx_lang ON lyrics(lang);
+         ANALYZE;"
+    ).unwrap();
+    eprintln!("Indexes in {:.1}s", t1.elapsed().as_secs_f64());
+
+    let mut kept_by_lang = HashMap::new();
+    for lc in &args.lang {
+        let cnt: i64 = dst.query_row(
+            "SELECT COUNT(*) FROM lyrics WHERE lang=?", params![lc], |r| r.get(0)
+        ).unwrap();
+        eprintln!("  {}: {}", lc, cnt);
+        kept_by_lang.insert(lc.clone(), cnt);
+    }
+
+    if let Some(path) = &args.report {
+        let report = FilterReport {
+            quality: stats[0],
+            lang: stats[1],
+            fp_dedup: stats[2],
+            near_dup: stats[3],
+            meta_dedup: stats[4],
+            kept: stats[5],
+            kept_by_lang,
+            elapsed_secs: elapsed,
+        };
+        if let Err(e) = write_report(path, &report) {
+            eprintln!("Failed to write report to {}: {}", path, e);
+        }
+    }
+
+    eprintln!("Done.");
+    Ok(())
+}
@@ -0,0 +1,26 @@
+// Shared helpers for `--report`/`REPORT_FORMAT`. JSON is the default; enabling
+// the `report-yaml` feature also allows YAML. CI parses this to mechanically
+// detect build regressions (e.g. kept dropped by >5%).
+
+use serde::Serialize;
+use std::env;
+
+pub fn write_report<T: Serialize>(path: &str, report: &T) -> std::io::Result<()> {
+    let format = env::var("REPORT_FORMAT").unwrap_or_else(|_| "json".to_string());
+    let text = match format.as_str() {
+        "yaml" => serialize_yaml(report),
+        _ => serde_json::to_string_pretty(report).unwrap_or_default(),
+    };
+    std::fs::write(path, text)
+}
+
+#[cfg(feature = "report-yaml")]
+fn serialize_yaml<T: Serialize>(report: &T) -> String {
+    serde_yaml::to_string(report).unwrap_or_default()
+}
+
+#[cfg(not(feature = "report-yaml"))]
+fn serialize_yaml<T: Serialize>(report: &T) -> String {
+    log::warn!("REPORT_FORMAT=yaml requested but built without the `report-yaml` feature; falling back to JSON.");
+    serde_json::to_string_pretty(report).unwrap_or_default()
+}
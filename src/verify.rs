@@ -0,0 +1,284 @@
+// Verification subsystem: offline integrity checks over a compiled DB.
+//
+// Handles both the `fetch` DB schema (video_id, is_synced, lrc) and the
+// `filter` output DB schema (id, track_name, artist_name, album_name,
+// duration, synced_lyrics, lang). Checks performed:
+//   1. LRC parse validation: `[mm:ss.xx]` timestamps are monotonically
+//      increasing, and rows marked synced have at least one timed line
+//   2. lang re-check: the stored lang agrees with a re-run of classify_lang
+//      (filter DB only; the fetch DB has no lang column)
+//   3. Fingerprint re-scan: lyrics_fingerprint collisions that slipped
+//      through dedup (filter DB only)
+//
+// `--repair` drops offending rows. Combined with `--requeue` (fetch DB
+// only), it also invalidates the affected video_id's cache entry so the
+// next `fetch` run re-downloads it.
+
+use crate::fetch::{CacheManifest, CACHE_NAME};
+use crate::filter::{classify_lang, lyrics_fingerprint, LangThresholds};
+use crate::report::write_report;
+use clap::Args;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Path to the compiled DB to verify
+    db: String,
+    /// Drop offending rows instead of only reporting them. On a fetch DB this is
+    /// permanent data loss on its own: the row's lrc_cache.json entry is left in
+    /// place, so an unchanged upstream manifest keeps returning 304 and the video_id
+    /// is never re-fetched. Pass --requeue alongside this to make the drop recoverable
+    #[arg(long)]
+    repair: bool,
+    /// In --repair mode on a fetch DB, also invalidate cache entries for
+    /// dropped video_ids so the next `fetch` run re-downloads them
+    #[arg(long)]
+    requeue: bool,
+    /// Write a structured verify report (JSON by default, see REPORT_FORMAT) to this path
+    #[arg(long)]
+    report: Option<String>,
+    /// Minimum byte length of timestamp-stripped lyrics text before classify_lang
+    /// attempts detection at all. Must match the `filter` run's value, or else a
+    /// filter DB built with non-default --lang-* thresholds will report false-positive
+    /// lang mismatches here (and --repair would delete correctly-tagged rows)
+    #[arg(long, default_value_t = 30)]
+    lang_min_bytes: usize,
+    /// Number of ja/ko script characters classify_lang requires before committing
+    /// to that language. Must match the `filter` run's value (see --lang-min-bytes)
+    #[arg(long, default_value_t = 10)]
+    lang_script_count: u32,
+    /// Number of Latin characters classify_lang requires before committing to "en".
+    /// Must match the `filter` run's value (see --lang-min-bytes)
+    #[arg(long, default_value_t = 30)]
+    lang_latin_count: u32,
+    /// Max count of excluded-script (Arabic/Cyrillic/Devanagari/Thai) characters
+    /// classify_lang tolerates before bailing out to unknown. Must match the
+    /// `filter` run's value (see --lang-min-bytes)
+    #[arg(long, default_value_t = 50)]
+    lang_exclude_count: u32,
+}
+
+/// Structured report for `--report`/`REPORT_FORMAT`.
+#[derive(Serialize)]
+struct VerifyReport {
+    checked: u64,
+    malformed_lrc: u64,
+    lang_mismatch: u64,
+    fp_collision: u64,
+    repaired: u64,
+    requeued: u64,
+    elapsed_secs: f64,
+}
+
+enum Offense {
+    MalformedLrc(String),
+    LangMismatch { stored: String, recomputed: Option<String> },
+    FpCollision(i64),
+}
+
+impl Offense {
+    fn describe(&self) -> String {
+        match self {
+            Offense::MalformedLrc(reason) => format!("malformed LRC: {}", reason),
+            Offense::LangMismatch { stored, recomputed } => format!(
+                "lang mismatch: stored={} recomputed={}",
+                stored,
+                recomputed.as_deref().unwrap_or("none")
+            ),
+            Offense::FpCollision(other_id) => format!("fingerprint collision with id={}", other_id),
+        }
+    }
+}
+
+/// Converts `[mm:ss.xx]` timestamps to seconds, in order of appearance.
+/// Returns an empty Vec if there are none (not an error).
+fn parse_timestamps(text: &str, re_ts: &Regex) -> Vec<f64> {
+    re_ts
+        .captures_iter(text)
+        .filter_map(|cap| {
+            let mm: f64 = cap.get(1)?.as_str().parse().ok()?;
+            let ss: f64 = cap.get(2)?.as_str().parse().ok()?;
+            Some(mm * 60.0 + ss)
+        })
+        .collect()
+}
+
+/// Validates that LRC text is well-formed: timestamps are monotonically
+/// increasing, and if `require_timed`, there's at least one timestamp.
+fn check_lrc(text: &str, re_ts: &Regex, require_timed: bool) -> Option<Offense> {
+    let timestamps = parse_timestamps(text, re_ts);
+    if timestamps.is_empty() {
+        if require_timed {
+            return Some(Offense::MalformedLrc("no timed lines found".to_string()));
+        }
+        return None;
+    }
+    for pair in timestamps.windows(2) {
+        if pair[1] < pair[0] {
+            return Some(Offense::MalformedLrc(format!(
+                "timestamps not monotonic ({:.2}s after {:.2}s)",
+                pair[1], pair[0]
+            )));
+        }
+    }
+    None
+}
+
+pub fn run(args: VerifyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("Verifying {} (repair={}, requeue={})...", args.db, args.repair, args.requeue);
+
+    let conn = Connection::open(&args.db)?;
+    let columns: HashSet<String> = {
+        let mut stmt = conn.prepare("PRAGMA table_info(lyrics)")?;
+        let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+        names.collect::<Result<_, _>>()?
+    };
+    let is_filter_db = columns.contains("synced_lyrics") && columns.contains("lang");
+    let is_fetch_db = columns.contains("video_id") && columns.contains("lrc");
+    if !is_filter_db && !is_fetch_db {
+        return Err(format!("{}: `lyrics` table doesn't match a known schema", args.db).into());
+    }
+
+    let re_ts = Regex::new(r"\[(\d+):(\d+(?:\.\d+)?)\]").unwrap();
+    let re_paren = Regex::new(r"[(（][^()（）]*[)）]").unwrap();
+    let re_non_word = Regex::new(r"[^\w]").unwrap();
+    let re_strip_ts = Regex::new(r"\[[\d:.]+\]").unwrap();
+
+    let t0 = Instant::now();
+    let mut stats = [0u64; 5]; // checked, malformed_lrc, lang_mismatch, fp_collision, repaired
+    let mut offenders: Vec<(i64, Offense)> = Vec::new();
+    let mut requeue_video_ids: Vec<String> = Vec::new();
+
+    if is_filter_db {
+        // If the DB being verified was built with non-default --lang-* values,
+        // the same values must be passed here too, or this reports false-positive
+        // lang mismatches (and --repair would delete correctly-tagged rows).
+        let lang_thresholds = LangThresholds {
+            min_bytes: args.lang_min_bytes,
+            script_count: args.lang_script_count,
+            latin_count: args.lang_latin_count,
+            exclude_count: args.lang_exclude_count,
+        };
+        let mut fp_seen: HashMap<[u8; 16], i64> = HashMap::with_capacity(5_000_000);
+        let mut stmt = conn.prepare("SELECT id, synced_lyrics, lang FROM lyrics ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let lyrics: String = row.get(1)?;
+            let lang: String = row.get(2)?;
+            stats[0] += 1;
+
+            if let Some(offense) = check_lrc(&lyrics, &re_ts, true) {
+                stats[1] += 1;
+                offenders.push((id, offense));
+                continue;
+            }
+
+            let text_no_ts = re_strip_ts.replace_all(&lyrics, "");
+            match classify_lang(&text_no_ts, &lang_thresholds) {
+                Some(recomputed) if recomputed == lang => {}
+                recomputed => {
+                    stats[2] += 1;
+                    offenders.push((
+                        id,
+                        Offense::LangMismatch { stored: lang, recomputed: recomputed.map(str::to_string) },
+                    ));
+                    continue;
+                }
+            }
+
+            if let Some(fp) = lyrics_fingerprint(&text_no_ts, &re_paren, &re_non_word) {
+                if let Some(&other_id) = fp_seen.get(&fp) {
+                    stats[3] += 1;
+                    offenders.push((id, Offense::FpCollision(other_id)));
+                    continue;
+                }
+                fp_seen.insert(fp, id);
+            }
+        }
+    } else {
+        // fetch DB: no lang/fingerprint to re-check, just LRC well-formedness.
+        let mut stmt = conn.prepare("SELECT rowid, video_id, is_synced, lrc FROM lyrics ORDER BY rowid")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let rowid: i64 = row.get(0)?;
+            let video_id: String = row.get(1)?;
+            let is_synced: bool = row.get(2)?;
+            let lrc: String = row.get(3)?;
+            stats[0] += 1;
+
+            if let Some(offense) = check_lrc(&lrc, &re_ts, is_synced) {
+                stats[1] += 1;
+                offenders.push((rowid, offense));
+                requeue_video_ids.push(video_id);
+            }
+        }
+    }
+
+    eprintln!("Checked {} rows in {:.1}s", stats[0], t0.elapsed().as_secs_f64());
+    for (id, offense) in &offenders {
+        eprintln!("  id={}: {}", id, offense.describe());
+    }
+
+    if args.repair && !offenders.is_empty() {
+        let id_column = if is_filter_db { "id" } else { "rowid" };
+        conn.execute_batch("BEGIN")?;
+        for (id, _) in &offenders {
+            conn.execute(
+                &format!("DELETE FROM lyrics WHERE {} = ?1", id_column),
+                params![id],
+            )?;
+            stats[4] += 1;
+        }
+        conn.execute_batch("COMMIT")?;
+        eprintln!("Repaired (dropped) {} rows.", stats[4]);
+    }
+
+    let mut requeued = 0u64;
+    if args.repair && is_fetch_db && !requeue_video_ids.is_empty() {
+        if args.requeue {
+            let mut cache = CacheManifest::load(CACHE_NAME);
+            for video_id in &requeue_video_ids {
+                if cache.repos.remove(video_id).is_some() {
+                    requeued += 1;
+                }
+            }
+            if let Err(e) = cache.save(CACHE_NAME) {
+                eprintln!("Failed to persist cache manifest {}: {}", CACHE_NAME, e);
+            } else {
+                eprintln!("Re-queued {} video_id(s) for the next fetch run.", requeued);
+            }
+        } else {
+            eprintln!(
+                "WARNING: dropped {} fetch DB row(s) without --requeue. Their {} entries \
+                 are untouched, so an unchanged upstream manifest will keep getting a 304 \
+                 and these video_id(s) will never be re-fetched. This data loss is permanent \
+                 unless you re-run with --requeue.",
+                requeue_video_ids.len(), CACHE_NAME,
+            );
+        }
+    }
+
+    let elapsed = t0.elapsed().as_secs_f64();
+    if let Some(path) = &args.report {
+        let report = VerifyReport {
+            checked: stats[0],
+            malformed_lrc: stats[1],
+            lang_mismatch: stats[2],
+            fp_collision: stats[3],
+            repaired: stats[4],
+            requeued,
+            elapsed_secs: elapsed,
+        };
+        if let Err(e) = write_report(path, &report) {
+            eprintln!("Failed to write report to {}: {}", path, e);
+        }
+    }
+
+    eprintln!("Done.");
+    Ok(())
+}